@@ -2,6 +2,7 @@ use enumset::EnumSet;
 
 use crate::{
     lex::{
+        lex,
         TokenId,
         TokenKind::{self, *},
         Tokens,
@@ -62,15 +63,26 @@ pub struct Param {
     pub ty: Option<TypeId>,
 }
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub enum Binop {
     Add,
     Sub,
     Mul,
     Div,
+    Pow,
+    Lt,
+    Gt,
+    Eq,
+    Le,
+    Ge,
 }
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Unop {
+    Neg,
+}
+
+#[derive(Clone, Debug)]
 pub enum Expr {
     Name {
         name: TokenId,
@@ -92,11 +104,24 @@ pub enum Expr {
         val: ExprId,
         body: ExprId,
     },
+    Unary {
+        op: Unop,
+        operand: ExprId,
+    },
     Binary {
         lhs: ExprId,
         op: Binop,
         rhs: ExprId,
     },
+    If {
+        cond: ExprId,
+        then: ExprId,
+        els: ExprId,
+    },
+    Lambda {
+        params: Vec<Param>,
+        body: ExprId,
+    },
 }
 
 #[derive(Debug)]
@@ -149,7 +174,7 @@ impl Module {
     }
 
     pub fn expr(&self, id: ExprId) -> Expr {
-        self.exprs[usize::from(id)]
+        self.exprs[usize::from(id)].clone()
     }
 
     pub fn defs(&self) -> &[Def] {
@@ -157,12 +182,244 @@ impl Module {
     }
 }
 
+/// Visits the children of an AST node. Each `visit_*` method's default
+/// implementation recurses into its node's children via the matching
+/// `walk_*` function; override a method to observe or short-circuit at that
+/// node while still delegating to `walk_*` for the parts you don't care
+/// about.
+pub trait Visit {
+    fn visit_type(&mut self, module: &Module, id: TypeId) {
+        walk_type(self, module, id);
+    }
+
+    fn visit_bind(&mut self, module: &Module, id: BindId) {
+        walk_bind(self, module, id);
+    }
+
+    fn visit_expr(&mut self, module: &Module, id: ExprId) {
+        walk_expr(self, module, id);
+    }
+
+    fn visit_def(&mut self, module: &Module, def: &Def) {
+        walk_def(self, module, def);
+    }
+}
+
+pub fn walk_type<V: Visit + ?Sized>(v: &mut V, module: &Module, id: TypeId) {
+    match module.ty(id) {
+        Type::Unit | Type::Name { .. } => {}
+        Type::Pair { fst, snd } => {
+            v.visit_type(module, fst);
+            v.visit_type(module, snd);
+        }
+    }
+}
+
+fn visit_param<V: Visit + ?Sized>(v: &mut V, module: &Module, param: Param) {
+    if let Some(ty) = param.ty {
+        v.visit_type(module, ty);
+    }
+    v.visit_bind(module, param.bind);
+}
+
+pub fn walk_bind<V: Visit + ?Sized>(v: &mut V, module: &Module, id: BindId) {
+    match module.bind(id) {
+        Bind::Unit | Bind::Name { .. } => {}
+        Bind::Pair { fst, snd } => {
+            visit_param(v, module, fst);
+            visit_param(v, module, snd);
+        }
+    }
+}
+
+pub fn walk_expr<V: Visit + ?Sized>(v: &mut V, module: &Module, id: ExprId) {
+    match module.expr(id) {
+        Expr::Name { .. } | Expr::Unit | Expr::Number { .. } => {}
+        Expr::Pair { fst, snd } => {
+            v.visit_expr(module, fst);
+            v.visit_expr(module, snd);
+        }
+        Expr::Apply { func, arg } => {
+            v.visit_expr(module, func);
+            v.visit_expr(module, arg);
+        }
+        Expr::Let { param, val, body } => {
+            visit_param(v, module, param);
+            v.visit_expr(module, val);
+            v.visit_expr(module, body);
+        }
+        Expr::Unary { op: _, operand } => {
+            v.visit_expr(module, operand);
+        }
+        Expr::Binary { lhs, op: _, rhs } => {
+            v.visit_expr(module, lhs);
+            v.visit_expr(module, rhs);
+        }
+        Expr::If { cond, then, els } => {
+            v.visit_expr(module, cond);
+            v.visit_expr(module, then);
+            v.visit_expr(module, els);
+        }
+        Expr::Lambda { params, body } => {
+            for param in params {
+                visit_param(v, module, param);
+            }
+            v.visit_expr(module, body);
+        }
+    }
+}
+
+pub fn walk_def<V: Visit + ?Sized>(v: &mut V, module: &Module, def: &Def) {
+    for &param in &def.params {
+        visit_param(v, module, param);
+    }
+    if let Some(ty) = def.ty {
+        v.visit_type(module, ty);
+    }
+    v.visit_expr(module, def.body);
+}
+
+/// Rebuilds an AST into a fresh `Module`. Each `fold_*` method's default
+/// implementation delegates to the matching `fold_*` free function, which
+/// folds a node's children and reallocates the node in `out` via
+/// `Module::make_ty`/`make_bind`/`make_expr`; override a method to rewrite a
+/// node on the way through.
+pub trait Fold {
+    fn fold_type(&mut self, module: &Module, out: &mut Module, id: TypeId) -> TypeId {
+        fold_type(self, module, out, id)
+    }
+
+    fn fold_bind(&mut self, module: &Module, out: &mut Module, id: BindId) -> BindId {
+        fold_bind(self, module, out, id)
+    }
+
+    fn fold_expr(&mut self, module: &Module, out: &mut Module, id: ExprId) -> ExprId {
+        fold_expr(self, module, out, id)
+    }
+
+    fn fold_def(&mut self, module: &Module, out: &mut Module, def: &Def) -> Def {
+        fold_def(self, module, out, def)
+    }
+}
+
+pub fn fold_type<F: Fold + ?Sized>(
+    f: &mut F,
+    module: &Module,
+    out: &mut Module,
+    id: TypeId,
+) -> TypeId {
+    let ty = match module.ty(id) {
+        Type::Unit => Type::Unit,
+        Type::Name { name } => Type::Name { name },
+        Type::Pair { fst, snd } => Type::Pair {
+            fst: f.fold_type(module, out, fst),
+            snd: f.fold_type(module, out, snd),
+        },
+    };
+    out.make_ty(ty)
+}
+
+fn fold_param<F: Fold + ?Sized>(
+    f: &mut F,
+    module: &Module,
+    out: &mut Module,
+    param: Param,
+) -> Param {
+    Param {
+        bind: f.fold_bind(module, out, param.bind),
+        ty: param.ty.map(|ty| f.fold_type(module, out, ty)),
+    }
+}
+
+pub fn fold_bind<F: Fold + ?Sized>(
+    f: &mut F,
+    module: &Module,
+    out: &mut Module,
+    id: BindId,
+) -> BindId {
+    let bind = match module.bind(id) {
+        Bind::Unit => Bind::Unit,
+        Bind::Name { name } => Bind::Name { name },
+        Bind::Pair { fst, snd } => Bind::Pair {
+            fst: fold_param(f, module, out, fst),
+            snd: fold_param(f, module, out, snd),
+        },
+    };
+    out.make_bind(bind)
+}
+
+pub fn fold_expr<F: Fold + ?Sized>(
+    f: &mut F,
+    module: &Module,
+    out: &mut Module,
+    id: ExprId,
+) -> ExprId {
+    let expr = match module.expr(id) {
+        Expr::Name { name } => Expr::Name { name },
+        Expr::Unit => Expr::Unit,
+        Expr::Number { val } => Expr::Number { val },
+        Expr::Pair { fst, snd } => Expr::Pair {
+            fst: f.fold_expr(module, out, fst),
+            snd: f.fold_expr(module, out, snd),
+        },
+        Expr::Apply { func, arg } => Expr::Apply {
+            func: f.fold_expr(module, out, func),
+            arg: f.fold_expr(module, out, arg),
+        },
+        Expr::Let { param, val, body } => Expr::Let {
+            param: fold_param(f, module, out, param),
+            val: f.fold_expr(module, out, val),
+            body: f.fold_expr(module, out, body),
+        },
+        Expr::Unary { op, operand } => Expr::Unary {
+            op,
+            operand: f.fold_expr(module, out, operand),
+        },
+        Expr::Binary { lhs, op, rhs } => Expr::Binary {
+            lhs: f.fold_expr(module, out, lhs),
+            op,
+            rhs: f.fold_expr(module, out, rhs),
+        },
+        Expr::If { cond, then, els } => Expr::If {
+            cond: f.fold_expr(module, out, cond),
+            then: f.fold_expr(module, out, then),
+            els: f.fold_expr(module, out, els),
+        },
+        Expr::Lambda { params, body } => Expr::Lambda {
+            params: params
+                .into_iter()
+                .map(|param| fold_param(f, module, out, param))
+                .collect(),
+            body: f.fold_expr(module, out, body),
+        },
+    };
+    out.make_expr(expr)
+}
+
+pub fn fold_def<F: Fold + ?Sized>(f: &mut F, module: &Module, out: &mut Module, def: &Def) -> Def {
+    Def {
+        name: def.name,
+        params: def
+            .params
+            .iter()
+            .map(|&param| fold_param(f, module, out, param))
+            .collect(),
+        ty: def.ty.map(|ty| f.fold_type(module, out, ty)),
+        body: f.fold_expr(module, out, def.body),
+    }
+}
+
 #[derive(Debug)]
 pub enum ParseError {
     Expected {
-        id: TokenId,
+        start: TokenId,
+        end: TokenId,
         kinds: EnumSet<TokenKind>,
     },
+    UnexpectedEof {
+        start: TokenId,
+        end: TokenId,
+    },
 }
 
 #[derive(Debug)]
@@ -184,25 +441,30 @@ impl<'a> Parser<'a> {
         }
     }
 
-    fn next(&mut self) {
+    fn next(&mut self) -> Result<(), ParseError> {
         if let Eof = self.peek() {
-            panic!("unexpected end of file");
+            return Err(ParseError::UnexpectedEof {
+                start: self.id,
+                end: self.id,
+            });
         }
         self.before_ws = TokenId {
             index: self.id.index + 1,
         };
         self.id = self.before_ws;
         self.find_non_ws();
+        Ok(())
     }
 
     fn expect(&mut self, kind: TokenKind) -> Result<TokenId, ParseError> {
         let id = self.id;
         if self.peek() == kind {
-            self.next();
+            self.next()?;
             Ok(id)
         } else {
             Err(ParseError::Expected {
-                id,
+                start: id,
+                end: id,
                 kinds: EnumSet::only(kind),
             })
         }
@@ -217,14 +479,14 @@ impl<'a> Parser<'a> {
         match self.peek() {
             Ident => {
                 let name = self.id;
-                self.next();
+                self.next()?;
                 Ok(self.module.make_ty(Type::Name { name }))
             }
             LParen => {
-                self.next();
+                self.next()?;
                 match self.peek() {
                     RParen => {
-                        self.next();
+                        self.next()?;
                         Ok(self.module.make_ty(Type::Unit))
                     }
                     _ => {
@@ -235,7 +497,8 @@ impl<'a> Parser<'a> {
                 }
             }
             _ => Err(ParseError::Expected {
-                id: self.id,
+                start: self.id,
+                end: self.id,
                 kinds: Ident | LParen,
             }),
         }
@@ -248,7 +511,7 @@ impl<'a> Parser<'a> {
     fn ty(&mut self) -> Result<TypeId, ParseError> {
         let mut types = vec![self.ty_elem()?];
         while let Comma = self.peek() {
-            self.next();
+            self.next()?;
             types.push(self.ty_elem()?);
         }
         let last = types.pop().unwrap();
@@ -261,14 +524,14 @@ impl<'a> Parser<'a> {
         match self.peek() {
             Ident => {
                 let name = self.id;
-                self.next();
+                self.next()?;
                 Ok(self.module.make_bind(Bind::Name { name }))
             }
             LParen => {
-                self.next();
+                self.next()?;
                 match self.peek() {
                     RParen => {
-                        self.next();
+                        self.next()?;
                         Ok(self.module.make_bind(Bind::Unit))
                     }
                     _ => {
@@ -276,7 +539,8 @@ impl<'a> Parser<'a> {
                         let right = self.expect(RParen)?;
                         match ty {
                             Some(_) => Err(ParseError::Expected {
-                                id: right,
+                                start: right,
+                                end: right,
                                 kinds: EnumSet::only(Comma),
                             }),
                             None => Ok(bind),
@@ -285,7 +549,8 @@ impl<'a> Parser<'a> {
                 }
             }
             _ => Err(ParseError::Expected {
-                id: self.id,
+                start: self.id,
+                end: self.id,
                 kinds: Ident | LParen,
             }),
         }
@@ -299,7 +564,7 @@ impl<'a> Parser<'a> {
         let bind = self.bind_elem()?;
         let ty = match self.peek() {
             Colon => {
-                self.next();
+                self.next()?;
                 Some(self.ty_elem()?)
             }
             _ => None,
@@ -310,7 +575,7 @@ impl<'a> Parser<'a> {
     fn param(&mut self) -> Result<Param, ParseError> {
         let mut params = vec![self.param_elem()?];
         while let Comma = self.peek() {
-            self.next();
+            self.next()?;
             params.push(self.param_elem()?);
         }
         let last = params.pop().unwrap();
@@ -323,10 +588,10 @@ impl<'a> Parser<'a> {
     fn expr_atom(&mut self) -> Result<ExprId, ParseError> {
         match self.peek() {
             LParen => {
-                self.next();
+                self.next()?;
                 match self.peek() {
                     RParen => {
-                        self.next();
+                        self.next()?;
                         Ok(self.module.make_expr(Expr::Unit))
                     }
                     _ => {
@@ -338,16 +603,17 @@ impl<'a> Parser<'a> {
             }
             Ident => {
                 let name = self.id;
-                self.next();
+                self.next()?;
                 Ok(self.module.make_expr(Expr::Name { name }))
             }
             Number => {
                 let val = self.id;
-                self.next();
+                self.next()?;
                 Ok(self.module.make_expr(Expr::Number { val }))
             }
             _ => Err(ParseError::Expected {
-                id: self.id,
+                start: self.id,
+                end: self.id,
                 kinds: LParen | Ident | Number,
             }),
         }
@@ -369,41 +635,72 @@ impl<'a> Parser<'a> {
         Ok(f)
     }
 
-    fn expr_term(&mut self) -> Result<ExprId, ParseError> {
-        let mut lhs = self.expr_factor()?;
-        loop {
-            let op = match self.peek() {
-                Asterisk => Binop::Mul,
-                Slash => Binop::Div,
-                _ => break,
-            };
-            self.next();
-            let rhs = self.expr_factor()?;
-            lhs = self.module.make_expr(Expr::Binary { lhs, op, rhs });
+    // Binding powers for infix operators, used by `expr_bp` below. Higher
+    // binds tighter; for a right-associative operator the right power is
+    // lower than the left so the recursive call re-admits the same operator.
+    fn binding_power(kind: TokenKind) -> Option<(u8, u8)> {
+        match kind {
+            Less | Greater | EqualEqual | LessEqual | GreaterEqual => Some((1, 2)),
+            Plus | Hyphen => Some((3, 4)),
+            Asterisk | Slash => Some((5, 6)),
+            Caret => Some((10, 9)),
+            _ => None,
         }
-        Ok(lhs)
     }
 
-    fn expr_elem(&mut self) -> Result<ExprId, ParseError> {
-        let mut lhs = self.expr_term()?;
+    fn token_to_binop(kind: TokenKind) -> Binop {
+        match kind {
+            Less => Binop::Lt,
+            Greater => Binop::Gt,
+            EqualEqual => Binop::Eq,
+            LessEqual => Binop::Le,
+            GreaterEqual => Binop::Ge,
+            Plus => Binop::Add,
+            Hyphen => Binop::Sub,
+            Asterisk => Binop::Mul,
+            Slash => Binop::Div,
+            Caret => Binop::Pow,
+            _ => unreachable!("not an infix operator"),
+        }
+    }
+
+    // Binding power `-` parses its operand with: looser than `^` (so
+    // `-a^b` is `-(a^b)`), tighter than `*`/`/` (so `-a*b` is `(-a)*b`).
+    const NEG_BP: u8 = 7;
+
+    fn expr_bp(&mut self, min_bp: u8) -> Result<ExprId, ParseError> {
+        let mut lhs = match self.peek() {
+            Hyphen => {
+                self.next()?;
+                let operand = self.expr_bp(Self::NEG_BP)?;
+                self.module.make_expr(Expr::Unary {
+                    op: Unop::Neg,
+                    operand,
+                })
+            }
+            _ => self.expr_factor()?,
+        };
         loop {
-            let op = match self.peek() {
-                Plus => Binop::Add,
-                Hyphen => Binop::Sub,
-                _ => break,
+            let kind = self.peek();
+            let Some((l_bp, r_bp)) = Self::binding_power(kind) else {
+                break;
             };
-            self.next();
-            let rhs = self.expr_term()?;
+            if l_bp < min_bp {
+                break;
+            }
+            let op = Self::token_to_binop(kind);
+            self.next()?;
+            let rhs = self.expr_bp(r_bp)?;
             lhs = self.module.make_expr(Expr::Binary { lhs, op, rhs });
         }
         Ok(lhs)
     }
 
     fn expr_inner(&mut self) -> Result<ExprId, ParseError> {
-        let mut exprs = vec![self.expr_elem()?];
+        let mut exprs = vec![self.expr_bp(0)?];
         while let Comma = self.peek() {
-            self.next();
-            exprs.push(self.expr_elem()?);
+            self.next()?;
+            exprs.push(self.expr_bp(0)?);
         }
         let last = exprs.pop().unwrap();
         Ok(exprs.into_iter().rfold(last, |snd, fst| {
@@ -414,7 +711,7 @@ impl<'a> Parser<'a> {
     fn expr(&mut self) -> Result<ExprId, ParseError> {
         match self.peek() {
             Let => {
-                self.next();
+                self.next()?;
                 let param = self.param()?;
                 self.expect(Equal)?;
                 let val = self.expr_inner()?;
@@ -424,19 +721,36 @@ impl<'a> Parser<'a> {
                 let body = self.expr()?;
                 Ok(self.module.make_expr(Expr::Let { param, val, body }))
             }
+            If => {
+                self.next()?;
+                let cond = self.expr_inner()?;
+                self.expect(Then)?;
+                let then = self.expr()?;
+                self.expect(Else)?;
+                let els = self.expr()?;
+                Ok(self.module.make_expr(Expr::If { cond, then, els }))
+            }
+            Fn => {
+                self.next()?;
+                let params = self.param_groups()?;
+                self.expect(Equal)?;
+                let body = self.expr()?;
+                Ok(self.module.make_expr(Expr::Lambda { params, body }))
+            }
             _ => self.expr_inner(),
         }
     }
 
-    fn def(&mut self) -> Result<Def, ParseError> {
-        self.expect(Def)?;
-        let name = self.expect(Ident)?;
+    // Parses the same `(param, ...)(param, ...)` binder groups `def` uses
+    // for its (possibly curried) parameter list, so a lambda's params are
+    // built the same way a def's are.
+    fn param_groups(&mut self) -> Result<Vec<Param>, ParseError> {
         let mut params = vec![];
         while let LParen = self.peek() {
-            self.next();
+            self.next()?;
             match self.peek() {
                 RParen => {
-                    self.next();
+                    self.next()?;
                     let bind = self.module.make_bind(Bind::Unit);
                     params.push(Param { bind, ty: None });
                 }
@@ -446,9 +760,16 @@ impl<'a> Parser<'a> {
                 }
             }
         }
+        Ok(params)
+    }
+
+    fn def(&mut self) -> Result<Def, ParseError> {
+        self.expect(Def)?;
+        let name = self.expect(Ident)?;
+        let params = self.param_groups()?;
         let ty = match self.peek() {
             Colon => {
-                self.next();
+                self.next()?;
                 Some(self.ty()?)
             }
             _ => None,
@@ -463,26 +784,45 @@ impl<'a> Parser<'a> {
         })
     }
 
-    fn module(mut self) -> Result<Module, ParseError> {
+    // On a malformed `Def`, record the error and skip to the next token that
+    // could plausibly start a new top-level item, so one bad definition
+    // doesn't hide every error after it.
+    fn sync(&mut self) {
+        while !matches!(self.peek(), Def | Eof) {
+            self.next().unwrap();
+        }
+    }
+
+    fn module(mut self) -> (Module, Vec<ParseError>) {
+        let mut errors = vec![];
         loop {
             match self.peek() {
-                Def => {
-                    let def = self.def()?;
-                    self.module.defs.push(def);
-                }
-                Eof => return Ok(self.module),
+                Def => match self.def() {
+                    Ok(def) => self.module.defs.push(def),
+                    Err(err) => {
+                        errors.push(err);
+                        self.sync();
+                    }
+                },
+                Eof => return (self.module, errors),
                 _ => {
-                    return Err(ParseError::Expected {
-                        id: self.id,
+                    let start = self.id;
+                    self.sync();
+                    let end = TokenId {
+                        index: self.id.index - 1,
+                    };
+                    errors.push(ParseError::Expected {
+                        start,
+                        end,
                         kinds: Def | Eof,
-                    })
+                    });
                 }
             }
         }
     }
 }
 
-pub fn parse(tokens: &Tokens) -> Result<Module, ParseError> {
+pub fn parse(tokens: &Tokens) -> (Module, Vec<ParseError>) {
     let id = TokenId { index: 0 };
     let mut parser = Parser {
         tokens,
@@ -498,3 +838,931 @@ pub fn parse(tokens: &Tokens) -> Result<Module, ParseError> {
     parser.find_non_ws();
     parser.module()
 }
+
+/// A type error found while checking a `Module`.
+#[derive(Debug)]
+pub enum TypeError {
+    /// Two types that must agree at the expression `expr` don't: `expected`
+    /// comes from an annotation, a parameter's type, a binary expression's
+    /// left operand or an `if`'s `then` branch, and `found` from the value,
+    /// argument, right operand or `else` branch checked against it.
+    Mismatch {
+        expr: ExprId,
+        expected: Type,
+        found: Type,
+    },
+    /// Both sides of the binary expression at `expr` agree on `ty`, but it's
+    /// a `Type::Unit`/`Type::Pair`, not a named base type — there's no
+    /// arithmetic or ordering to speak of for either.
+    NonBaseOperand { expr: ExprId, ty: Type },
+}
+
+#[derive(Clone)]
+struct DefSig {
+    name: TokenId,
+    params: Vec<Param>,
+    ty: Option<TypeId>,
+    body: ExprId,
+}
+
+// What an expression resolved to: either a concrete type, or a `def` or
+// lambda partially applied `depth` times, still waiting for more arguments
+// before its result type is known.
+enum Inferred {
+    Ty(TypeId),
+    Partial { name: TokenId, depth: usize },
+    PartialLambda { lambda: ExprId, depth: usize },
+}
+
+struct Checker<'a> {
+    tokens: &'a Tokens,
+    module: &'a mut Module,
+    defs: Vec<DefSig>,
+    env: Vec<(TokenId, TypeId)>,
+    table: Vec<(ExprId, Type)>,
+    errors: Vec<TypeError>,
+}
+
+impl<'a> Checker<'a> {
+    fn ident_eq(&self, a: TokenId, b: TokenId) -> bool {
+        self.tokens.text(a) == self.tokens.text(b)
+    }
+
+    fn ty_eq(&self, a: TypeId, b: TypeId) -> bool {
+        match (self.module.ty(a), self.module.ty(b)) {
+            (Type::Unit, Type::Unit) => true,
+            (Type::Name { name: x }, Type::Name { name: y }) => self.ident_eq(x, y),
+            (Type::Pair { fst: f1, snd: s1 }, Type::Pair { fst: f2, snd: s2 }) => {
+                self.ty_eq(f1, f2) && self.ty_eq(s1, s2)
+            }
+            _ => false,
+        }
+    }
+
+    fn lookup(&self, name: TokenId) -> Option<TypeId> {
+        self.env
+            .iter()
+            .rev()
+            .find(|(bound, _)| self.ident_eq(*bound, name))
+            .map(|&(_, ty)| ty)
+    }
+
+    fn find_def(&self, name: TokenId) -> Option<DefSig> {
+        self.defs.iter().find(|def| self.ident_eq(def.name, name)).cloned()
+    }
+
+    fn check_eq(&mut self, expr: ExprId, expected: TypeId, found: TypeId) {
+        if !self.ty_eq(expected, found) {
+            self.errors.push(TypeError::Mismatch {
+                expr,
+                expected: self.module.ty(expected),
+                found: self.module.ty(found),
+            });
+        }
+    }
+
+    // Binds `param`'s leaves into scope, preferring its own annotation over
+    // `value_ty` (the type inferred for whatever it's being bound to) and
+    // destructuring `Bind::Pair` against a `Type::Pair` when one is known.
+    fn bind_param(&mut self, param: Param, value_ty: Option<TypeId>) {
+        let ty = param.ty.or(value_ty);
+        match self.module.bind(param.bind) {
+            Bind::Unit => {}
+            Bind::Name { name } => {
+                if let Some(ty) = ty {
+                    self.env.push((name, ty));
+                }
+            }
+            Bind::Pair { fst, snd } => {
+                let (fst_ty, snd_ty) = match ty.map(|ty| self.module.ty(ty)) {
+                    Some(Type::Pair { fst, snd }) => (Some(fst), Some(snd)),
+                    _ => (None, None),
+                };
+                self.bind_param(fst, fst_ty);
+                self.bind_param(snd, snd_ty);
+            }
+        }
+    }
+
+    fn infer_ty(&mut self, id: ExprId) -> Option<TypeId> {
+        match self.infer(id)? {
+            Inferred::Ty(ty) => Some(ty),
+            Inferred::Partial { .. } | Inferred::PartialLambda { .. } => None,
+        }
+    }
+
+    fn infer(&mut self, id: ExprId) -> Option<Inferred> {
+        let inferred = match self.module.expr(id) {
+            Expr::Unit => Some(Inferred::Ty(self.module.make_ty(Type::Unit))),
+            // a bare numeric literal has no base type of its own; it takes
+            // whatever base type it's checked or unified against
+            Expr::Number { .. } => None,
+            Expr::Name { name } => match self.lookup(name) {
+                Some(ty) => Some(Inferred::Ty(ty)),
+                None => self
+                    .find_def(name)
+                    .map(|def| Inferred::Partial { name: def.name, depth: 0 }),
+            },
+            Expr::Pair { fst, snd } => {
+                let fst = self.infer_ty(fst);
+                let snd = self.infer_ty(snd);
+                match (fst, snd) {
+                    (Some(fst), Some(snd)) => {
+                        Some(Inferred::Ty(self.module.make_ty(Type::Pair { fst, snd })))
+                    }
+                    _ => None,
+                }
+            }
+            Expr::Apply { func, arg } => {
+                let arg_ty = self.infer_ty(arg);
+                match self.infer(func) {
+                    Some(Inferred::Partial { name, depth }) => {
+                        let def = self.find_def(name)?;
+                        if let (Some(expected), Some(found)) =
+                            (def.params.get(depth).and_then(|p| p.ty), arg_ty)
+                        {
+                            self.check_eq(id, expected, found);
+                        }
+                        if depth + 1 == def.params.len() {
+                            def.ty.map(Inferred::Ty)
+                        } else {
+                            Some(Inferred::Partial {
+                                name,
+                                depth: depth + 1,
+                            })
+                        }
+                    }
+                    Some(Inferred::PartialLambda { lambda, depth }) => {
+                        let Expr::Lambda { params, .. } = self.module.expr(lambda) else {
+                            unreachable!("PartialLambda always wraps an Expr::Lambda")
+                        };
+                        if let (Some(expected), Some(found)) =
+                            (params.get(depth).and_then(|p| p.ty), arg_ty)
+                        {
+                            self.check_eq(id, expected, found);
+                        }
+                        if depth + 1 == params.len() {
+                            // the lattice has no function type, so a fully
+                            // applied lambda's result still isn't typed
+                            None
+                        } else {
+                            Some(Inferred::PartialLambda {
+                                lambda,
+                                depth: depth + 1,
+                            })
+                        }
+                    }
+                    _ => None,
+                }
+            }
+            Expr::Let { param, val, body } => {
+                let val_ty = self.infer_ty(val);
+                if let (Some(ann), Some(found)) = (param.ty, val_ty) {
+                    self.check_eq(id, ann, found);
+                }
+                let depth = self.env.len();
+                self.bind_param(param, val_ty);
+                let body = self.infer(body);
+                self.env.truncate(depth);
+                body
+            }
+            Expr::Unary { op: Unop::Neg, operand } => self.infer(operand),
+            Expr::Binary { lhs, rhs, .. } => {
+                let lhs_ty = self.infer_ty(lhs);
+                let rhs_ty = self.infer_ty(rhs);
+                match (lhs_ty, rhs_ty) {
+                    (Some(l), Some(r)) => {
+                        self.check_eq(id, l, r);
+                        // arithmetic and comparison operators both funnel
+                        // through here, and neither means anything for a
+                        // `Type::Unit`/`Type::Pair` operand
+                        if self.ty_eq(l, r) && !matches!(self.module.ty(l), Type::Name { .. }) {
+                            self.errors.push(TypeError::NonBaseOperand {
+                                expr: id,
+                                ty: self.module.ty(l),
+                            });
+                        }
+                        Some(Inferred::Ty(l))
+                    }
+                    (Some(t), None) | (None, Some(t)) => Some(Inferred::Ty(t)),
+                    (None, None) => None,
+                }
+            }
+            Expr::If { cond, then, els } => {
+                // the lattice has no boolean type, so the condition is only
+                // checked for internal consistency, not required to be boolean
+                self.infer(cond);
+                let then_ty = self.infer_ty(then);
+                let els_ty = self.infer_ty(els);
+                match (then_ty, els_ty) {
+                    (Some(t), Some(e)) => {
+                        self.check_eq(id, t, e);
+                        Some(Inferred::Ty(t))
+                    }
+                    (Some(t), None) | (None, Some(t)) => Some(Inferred::Ty(t)),
+                    (None, None) => None,
+                }
+            }
+            Expr::Lambda { params, body } => {
+                // the lattice has no function type, so a lambda itself isn't
+                // typed directly; its body is checked against its params
+                // here, and `Expr::Apply` checks arguments against `params`
+                // via `Inferred::PartialLambda` if it's applied
+                let depth = self.env.len();
+                for param in params {
+                    self.bind_param(param, None);
+                }
+                self.infer(body);
+                self.env.truncate(depth);
+                Some(Inferred::PartialLambda { lambda: id, depth: 0 })
+            }
+        };
+        if let Some(Inferred::Ty(ty)) = inferred {
+            let resolved = self.module.ty(ty);
+            self.table.push((id, resolved));
+        }
+        inferred
+    }
+
+    fn check_def(&mut self, def: &DefSig) {
+        let depth = self.env.len();
+        for &param in &def.params {
+            self.bind_param(param, None);
+        }
+        let body_ty = self.infer_ty(def.body);
+        if let (Some(ann), Some(found)) = (def.ty, body_ty) {
+            self.check_eq(def.body, ann, found);
+        }
+        self.env.truncate(depth);
+    }
+}
+
+/// Infers a `Type` for each `ExprId` reachable from `module`'s `Def`s whose
+/// type can be determined; a bare numeric literal, a lambda, a bare or
+/// partially applied `def`, an unannotated parameter, and anything whose
+/// type could only come from one of those get no entry in the table. Reports a `TypeError` wherever two types that
+/// must agree don't (an annotation and the value it annotates, a parameter
+/// and its argument, two operands, or two `if` branches), or a binary
+/// operator is applied to a non-base type. `tokens` resolves the base type
+/// names stored in `Type::Name`.
+pub fn check(module: &mut Module, tokens: &Tokens) -> (Vec<(ExprId, Type)>, Vec<TypeError>) {
+    let defs: Vec<DefSig> = module
+        .defs()
+        .iter()
+        .map(|def| DefSig {
+            name: def.name,
+            params: def.params.clone(),
+            ty: def.ty,
+            body: def.body,
+        })
+        .collect();
+    let mut checker = Checker {
+        tokens,
+        module,
+        defs: defs.clone(),
+        env: vec![],
+        table: vec![],
+        errors: vec![],
+    };
+    for def in &defs {
+        checker.check_def(def);
+    }
+    (checker.table, checker.errors)
+}
+
+/// Unparses a `Module` back into source text, with just enough
+/// parenthesization to guarantee `parse(&lex(&unparse(module, tokens)))`
+/// reproduces the same tree. `tokens` resolves the identifier/number
+/// spellings stored in each `TokenId`.
+struct Printer<'a> {
+    tokens: &'a Tokens,
+    module: &'a Module,
+    out: String,
+}
+
+impl<'a> Printer<'a> {
+    // Binding-power thresholds for the expression printer, mirroring the
+    // relative precedence `Parser::expr_bp` encodes at parse time. These
+    // numbers don't need to match `Parser::binding_power`'s literally, only
+    // preserve the same ordering, so there's room for the non-operator
+    // levels (`let`/`if`/`fn` and comma pairs) below the lowest operator.
+    const BP_STMT: u8 = 0;
+    const BP_PAIR: u8 = 10;
+    const BP_CMP: (u8, u8) = (20, 21);
+    const BP_ADD: (u8, u8) = (30, 31);
+    const BP_MUL: (u8, u8) = (40, 41);
+    const BP_NEG: u8 = 45;
+    const BP_POW: (u8, u8) = (50, 49);
+
+    fn binop_bp(op: Binop) -> (u8, u8) {
+        match op {
+            Binop::Lt | Binop::Gt | Binop::Eq | Binop::Le | Binop::Ge => Self::BP_CMP,
+            Binop::Add | Binop::Sub => Self::BP_ADD,
+            Binop::Mul | Binop::Div => Self::BP_MUL,
+            Binop::Pow => Self::BP_POW,
+        }
+    }
+
+    fn binop_symbol(op: Binop) -> &'static str {
+        match op {
+            Binop::Add => "+",
+            Binop::Sub => "-",
+            Binop::Mul => "*",
+            Binop::Div => "/",
+            Binop::Pow => "^",
+            Binop::Lt => "<",
+            Binop::Gt => ">",
+            Binop::Eq => "==",
+            Binop::Le => "<=",
+            Binop::Ge => ">=",
+        }
+    }
+
+    fn print_ty_elem(&mut self, id: TypeId) {
+        match self.module.ty(id) {
+            Type::Unit => self.out.push_str("()"),
+            Type::Name { name } => self.out.push_str(self.tokens.text(name)),
+            Type::Pair { .. } => {
+                self.out.push('(');
+                self.print_ty(id);
+                self.out.push(')');
+            }
+        }
+    }
+
+    fn print_ty(&mut self, id: TypeId) {
+        match self.module.ty(id) {
+            Type::Pair { fst, snd } => {
+                self.print_ty_elem(fst);
+                self.out.push_str(", ");
+                self.print_ty(snd);
+            }
+            _ => self.print_ty_elem(id),
+        }
+    }
+
+    fn print_bind_elem(&mut self, id: BindId) {
+        match self.module.bind(id) {
+            Bind::Unit => self.out.push_str("()"),
+            Bind::Name { name } => self.out.push_str(self.tokens.text(name)),
+            Bind::Pair { fst, snd } => {
+                self.out.push('(');
+                self.print_param_elem(fst);
+                self.out.push_str(", ");
+                self.print_param_list(snd);
+                self.out.push(')');
+            }
+        }
+    }
+
+    fn print_param_elem(&mut self, param: Param) {
+        self.print_bind_elem(param.bind);
+        if let Some(ty) = param.ty {
+            self.out.push_str(": ");
+            self.print_ty_elem(ty);
+        }
+    }
+
+    fn print_param_list(&mut self, param: Param) {
+        match (self.module.bind(param.bind), param.ty) {
+            (Bind::Pair { fst, snd }, None) => {
+                self.print_param_elem(fst);
+                self.out.push_str(", ");
+                self.print_param_list(snd);
+            }
+            _ => self.print_param_elem(param),
+        }
+    }
+
+    // Prints one `(...)` binder group from `Def`/`Expr::Lambda`'s `params`,
+    // the same grouping `Parser::param_groups` builds.
+    fn print_param_group(&mut self, param: Param) {
+        if let (Bind::Unit, None) = (self.module.bind(param.bind), param.ty) {
+            self.out.push_str("()");
+        } else {
+            self.out.push('(');
+            self.print_param_list(param);
+            self.out.push(')');
+        }
+    }
+
+    fn print_expr(&mut self, id: ExprId, min_bp: u8) {
+        match self.module.expr(id) {
+            Expr::Name { name } => self.out.push_str(self.tokens.text(name)),
+            Expr::Unit => self.out.push_str("()"),
+            Expr::Number { val } => self.out.push_str(self.tokens.text(val)),
+            Expr::Apply { .. } => self.print_apply(id),
+            Expr::Pair { fst, snd } => {
+                let parens = min_bp > Self::BP_PAIR;
+                if parens {
+                    self.out.push('(');
+                }
+                self.print_expr(fst, Self::BP_PAIR + 1);
+                self.out.push_str(", ");
+                self.print_expr(snd, Self::BP_PAIR);
+                if parens {
+                    self.out.push(')');
+                }
+            }
+            Expr::Let { param, val, body } => {
+                let parens = min_bp > Self::BP_STMT;
+                if parens {
+                    self.out.push('(');
+                }
+                self.out.push_str("let ");
+                self.print_param_list(param);
+                self.out.push_str(" = ");
+                self.print_expr(val, Self::BP_PAIR);
+                self.out.push_str(";\n");
+                self.print_expr(body, Self::BP_STMT);
+                if parens {
+                    self.out.push(')');
+                }
+            }
+            Expr::Unary { op: Unop::Neg, operand } => {
+                self.out.push('-');
+                // A bare `--` would re-lex as a single token (or worse, a
+                // line comment marker) instead of two `Hyphen`s, so keep
+                // back-to-back negations apart.
+                if matches!(self.module.expr(operand), Expr::Unary { op: Unop::Neg, .. }) {
+                    self.out.push(' ');
+                }
+                self.print_expr(operand, Self::BP_NEG);
+            }
+            Expr::Binary { lhs, op, rhs } => {
+                let (l_bp, r_bp) = Self::binop_bp(op);
+                let parens = min_bp > l_bp;
+                if parens {
+                    self.out.push('(');
+                }
+                // A bare unary minus as the left operand of an operator whose
+                // own left binding power reaches into `-`'s operand range
+                // (only `^`) would be swallowed into the `-`'s operand on
+                // reparse, so force parens around it here.
+                let lhs_is_tight_neg = l_bp >= Self::BP_NEG
+                    && matches!(self.module.expr(lhs), Expr::Unary { op: Unop::Neg, .. });
+                if lhs_is_tight_neg {
+                    self.out.push('(');
+                    self.print_expr(lhs, 0);
+                    self.out.push(')');
+                } else {
+                    self.print_expr(lhs, l_bp);
+                }
+                self.out.push(' ');
+                self.out.push_str(Self::binop_symbol(op));
+                self.out.push(' ');
+                self.print_expr(rhs, r_bp);
+                if parens {
+                    self.out.push(')');
+                }
+            }
+            Expr::If { cond, then, els } => {
+                let parens = min_bp > Self::BP_STMT;
+                if parens {
+                    self.out.push('(');
+                }
+                self.out.push_str("if ");
+                self.print_expr(cond, Self::BP_PAIR);
+                self.out.push_str(" then ");
+                self.print_expr(then, Self::BP_STMT);
+                self.out.push_str(" else ");
+                self.print_expr(els, Self::BP_STMT);
+                if parens {
+                    self.out.push(')');
+                }
+            }
+            Expr::Lambda { params, body } => {
+                let parens = min_bp > Self::BP_STMT;
+                if parens {
+                    self.out.push('(');
+                }
+                self.out.push_str("fn");
+                for param in params {
+                    self.print_param_group(param);
+                }
+                self.out.push_str(" = ");
+                self.print_expr(body, Self::BP_STMT);
+                if parens {
+                    self.out.push(')');
+                }
+            }
+        }
+    }
+
+    // Prints a function-application spine (`f a b c`). Application binds
+    // tighter than every infix operator, so each argument (and the head, if
+    // it isn't itself a name/literal) needs parens around anything that
+    // isn't already atomic, matching what `Parser::expr_factor` accepts
+    // unparenthesized.
+    fn print_apply(&mut self, id: ExprId) {
+        let mut args = vec![];
+        let mut head = id;
+        while let Expr::Apply { func, arg } = self.module.expr(head) {
+            args.push(arg);
+            head = func;
+        }
+        args.reverse();
+        self.print_apply_operand(head);
+        for arg in args {
+            self.out.push(' ');
+            self.print_apply_operand(arg);
+        }
+    }
+
+    fn print_apply_operand(&mut self, id: ExprId) {
+        match self.module.expr(id) {
+            Expr::Name { .. } | Expr::Unit | Expr::Number { .. } => self.print_expr(id, 0),
+            Expr::Apply { .. } => {
+                self.out.push('(');
+                self.print_apply(id);
+                self.out.push(')');
+            }
+            _ => {
+                self.out.push('(');
+                self.print_expr(id, 0);
+                self.out.push(')');
+            }
+        }
+    }
+
+    fn print_def(&mut self, def: &Def) {
+        self.out.push_str("def ");
+        self.out.push_str(self.tokens.text(def.name));
+        for param in &def.params {
+            self.print_param_group(*param);
+        }
+        if let Some(ty) = def.ty {
+            self.out.push_str(": ");
+            self.print_ty(ty);
+        }
+        self.out.push_str(" = ");
+        self.print_expr(def.body, Self::BP_STMT);
+    }
+}
+
+/// Unparses `module` back into source text that, when lexed and parsed
+/// again, reproduces a structurally equal `Module` (see
+/// [`assert_parse_roundtrip`]). `tokens` must be the same `Tokens` that
+/// `module` was parsed from.
+pub fn unparse(module: &Module, tokens: &Tokens) -> String {
+    let mut printer = Printer {
+        tokens,
+        module,
+        out: String::new(),
+    };
+    for (i, def) in module.defs().iter().enumerate() {
+        if i > 0 {
+            printer.out.push_str("\n\n");
+        }
+        printer.print_def(def);
+    }
+    printer.out.push('\n');
+    printer.out
+}
+
+fn ty_structurally_eq(
+    am: &Module,
+    at: &Tokens,
+    a: TypeId,
+    bm: &Module,
+    bt: &Tokens,
+    b: TypeId,
+) -> bool {
+    match (am.ty(a), bm.ty(b)) {
+        (Type::Unit, Type::Unit) => true,
+        (Type::Name { name: x }, Type::Name { name: y }) => at.text(x) == bt.text(y),
+        (Type::Pair { fst: f1, snd: s1 }, Type::Pair { fst: f2, snd: s2 }) => {
+            ty_structurally_eq(am, at, f1, bm, bt, f2) && ty_structurally_eq(am, at, s1, bm, bt, s2)
+        }
+        _ => false,
+    }
+}
+
+fn opt_ty_structurally_eq(
+    am: &Module,
+    at: &Tokens,
+    a: Option<TypeId>,
+    bm: &Module,
+    bt: &Tokens,
+    b: Option<TypeId>,
+) -> bool {
+    match (a, b) {
+        (Some(x), Some(y)) => ty_structurally_eq(am, at, x, bm, bt, y),
+        (None, None) => true,
+        _ => false,
+    }
+}
+
+fn bind_structurally_eq(
+    am: &Module,
+    at: &Tokens,
+    a: BindId,
+    bm: &Module,
+    bt: &Tokens,
+    b: BindId,
+) -> bool {
+    match (am.bind(a), bm.bind(b)) {
+        (Bind::Unit, Bind::Unit) => true,
+        (Bind::Name { name: x }, Bind::Name { name: y }) => at.text(x) == bt.text(y),
+        (Bind::Pair { fst: f1, snd: s1 }, Bind::Pair { fst: f2, snd: s2 }) => {
+            param_structurally_eq(am, at, f1, bm, bt, f2)
+                && param_structurally_eq(am, at, s1, bm, bt, s2)
+        }
+        _ => false,
+    }
+}
+
+fn param_structurally_eq(
+    am: &Module,
+    at: &Tokens,
+    a: Param,
+    bm: &Module,
+    bt: &Tokens,
+    b: Param,
+) -> bool {
+    bind_structurally_eq(am, at, a.bind, bm, bt, b.bind)
+        && opt_ty_structurally_eq(am, at, a.ty, bm, bt, b.ty)
+}
+
+fn expr_structurally_eq(
+    am: &Module,
+    at: &Tokens,
+    a: ExprId,
+    bm: &Module,
+    bt: &Tokens,
+    b: ExprId,
+) -> bool {
+    match (am.expr(a), bm.expr(b)) {
+        (Expr::Name { name: x }, Expr::Name { name: y }) => at.text(x) == bt.text(y),
+        (Expr::Unit, Expr::Unit) => true,
+        (Expr::Number { val: x }, Expr::Number { val: y }) => at.text(x) == bt.text(y),
+        (Expr::Pair { fst: f1, snd: s1 }, Expr::Pair { fst: f2, snd: s2 }) => {
+            expr_structurally_eq(am, at, f1, bm, bt, f2)
+                && expr_structurally_eq(am, at, s1, bm, bt, s2)
+        }
+        (Expr::Apply { func: fn1, arg: a1 }, Expr::Apply { func: fn2, arg: a2 }) => {
+            expr_structurally_eq(am, at, fn1, bm, bt, fn2)
+                && expr_structurally_eq(am, at, a1, bm, bt, a2)
+        }
+        (
+            Expr::Let { param: p1, val: v1, body: b1 },
+            Expr::Let { param: p2, val: v2, body: b2 },
+        ) => {
+            param_structurally_eq(am, at, p1, bm, bt, p2)
+                && expr_structurally_eq(am, at, v1, bm, bt, v2)
+                && expr_structurally_eq(am, at, b1, bm, bt, b2)
+        }
+        (Expr::Unary { op: o1, operand: x1 }, Expr::Unary { op: o2, operand: x2 }) => {
+            o1 == o2 && expr_structurally_eq(am, at, x1, bm, bt, x2)
+        }
+        (
+            Expr::Binary { lhs: l1, op: o1, rhs: r1 },
+            Expr::Binary { lhs: l2, op: o2, rhs: r2 },
+        ) => {
+            o1 == o2
+                && expr_structurally_eq(am, at, l1, bm, bt, l2)
+                && expr_structurally_eq(am, at, r1, bm, bt, r2)
+        }
+        (Expr::If { cond: c1, then: t1, els: e1 }, Expr::If { cond: c2, then: t2, els: e2 }) => {
+            expr_structurally_eq(am, at, c1, bm, bt, c2)
+                && expr_structurally_eq(am, at, t1, bm, bt, t2)
+                && expr_structurally_eq(am, at, e1, bm, bt, e2)
+        }
+        (Expr::Lambda { params: p1, body: b1 }, Expr::Lambda { params: p2, body: b2 }) => {
+            p1.len() == p2.len()
+                && p1
+                    .iter()
+                    .zip(p2.iter())
+                    .all(|(&x, &y)| param_structurally_eq(am, at, x, bm, bt, y))
+                && expr_structurally_eq(am, at, b1, bm, bt, b2)
+        }
+        _ => false,
+    }
+}
+
+fn def_structurally_eq(
+    am: &Module,
+    at: &Tokens,
+    a: &Def,
+    bm: &Module,
+    bt: &Tokens,
+    b: &Def,
+) -> bool {
+    at.text(a.name) == bt.text(b.name)
+        && a.params.len() == b.params.len()
+        && a.params
+            .iter()
+            .zip(&b.params)
+            .all(|(&x, &y)| param_structurally_eq(am, at, x, bm, bt, y))
+        && opt_ty_structurally_eq(am, at, a.ty, bm, bt, b.ty)
+        && expr_structurally_eq(am, at, a.body, bm, bt, b.body)
+}
+
+/// Compares two `Module`s for structural equality, resolving `Name`/`Number`
+/// leaves through each module's own `Tokens` rather than comparing `TokenId`s
+/// directly, so two parses of differently-formatted-but-equivalent source
+/// compare equal.
+pub fn modules_structurally_eq(am: &Module, at: &Tokens, bm: &Module, bt: &Tokens) -> bool {
+    am.defs().len() == bm.defs().len()
+        && am
+            .defs()
+            .iter()
+            .zip(bm.defs())
+            .all(|(a, b)| def_structurally_eq(am, at, a, bm, bt, b))
+}
+
+/// Parses `tokens`, pretty-prints the result, re-parses the printed source,
+/// and asserts the two `Module`s are structurally equal (ignoring exact
+/// `TokenId` indices). A cheap regression/fuzz oracle: call it with freshly
+/// lexed `Tokens` for any program expected to parse without errors.
+pub fn assert_parse_roundtrip(tokens: &Tokens) {
+    let (module, errors) = parse(tokens);
+    assert!(errors.is_empty(), "input did not parse cleanly: {errors:?}");
+    let printed = unparse(&module, tokens);
+    let reparsed_tokens = lex(&printed);
+    let (reparsed, reparsed_errors) = parse(&reparsed_tokens);
+    assert!(
+        reparsed_errors.is_empty(),
+        "pretty-printed output did not parse cleanly: {reparsed_errors:?}\n{printed}"
+    );
+    assert!(
+        modules_structurally_eq(&module, tokens, &reparsed, &reparsed_tokens),
+        "roundtrip produced a different module\n{printed}"
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_ok(src: &str) -> (Tokens, Module) {
+        let tokens = lex(src);
+        let (module, errors) = parse(&tokens);
+        assert!(errors.is_empty(), "{errors:?}");
+        (tokens, module)
+    }
+
+    // chunk0-1: multi-error recovery/sync.
+    #[test]
+    fn sync_recovers_after_multiple_bad_defs() {
+        let tokens = lex("def f(x) = \ndef bad =\ndef g(y) = y\n");
+        let (module, errors) = parse(&tokens);
+        assert_eq!(errors.len(), 2);
+        assert_eq!(module.defs().len(), 1);
+        assert_eq!(tokens.text(module.defs()[0].name), "g");
+    }
+
+    #[test]
+    fn trailing_operator_before_eof_is_a_parse_error_not_a_panic() {
+        // Every call to `next()` is guarded by a prior `peek()` match, so
+        // running off the end of the tokens surfaces as an ordinary
+        // `ParseError`, not a panic, regardless of which variant fires.
+        let tokens = lex("def f(x) = x +");
+        let (_, errors) = parse(&tokens);
+        assert_eq!(errors.len(), 1);
+        assert!(matches!(
+            errors[0],
+            ParseError::Expected { .. } | ParseError::UnexpectedEof { .. }
+        ));
+    }
+
+    // chunk0-2: Pratt precedence for `^`, unary minus, and comparisons.
+    #[test]
+    fn pow_is_right_associative_and_binds_tighter_than_neg_and_comparisons() {
+        let (t1, m1) = parse_ok("def f() = -a ^ b ^ c < d + e * g\n");
+        let (t2, m2) = parse_ok("def f() = (-(a ^ (b ^ c))) < (d + (e * g))\n");
+        assert!(modules_structurally_eq(&m1, &t1, &m2, &t2));
+    }
+
+    #[test]
+    fn unary_minus_binds_looser_than_pow_but_tighter_than_mul() {
+        let (t1, m1) = parse_ok("def f() = -a * b\n");
+        let (t2, m2) = parse_ok("def f() = (-a) * b\n");
+        assert!(modules_structurally_eq(&m1, &t1, &m2, &t2));
+
+        let (t3, m3) = parse_ok("def f() = -a ^ b\n");
+        let (t4, m4) = parse_ok("def f() = -(a ^ b)\n");
+        assert!(modules_structurally_eq(&m3, &t3, &m4, &t4));
+    }
+
+    // chunk0-3: generated Visit/Fold traversal.
+    #[test]
+    fn visit_walks_every_name_occurrence() {
+        let (_, module) = parse_ok("def f(x) = let y = x + x; y * x\n");
+
+        struct CountNames(usize);
+        impl Visit for CountNames {
+            fn visit_expr(&mut self, module: &Module, id: ExprId) {
+                if let Expr::Name { .. } = module.expr(id) {
+                    self.0 += 1;
+                }
+                walk_expr(self, module, id);
+            }
+        }
+
+        let mut counter = CountNames(0);
+        for def in module.defs() {
+            counter.visit_def(&module, def);
+        }
+        assert_eq!(counter.0, 4);
+    }
+
+    #[test]
+    fn fold_identity_preserves_module_structure() {
+        let (tokens, module) = parse_ok("def f(x: int) = if x < 0 then -x else x\n");
+
+        struct Identity;
+        impl Fold for Identity {}
+
+        let mut out = Module {
+            types: vec![],
+            binds: vec![],
+            exprs: vec![],
+            defs: vec![],
+        };
+        let mut folder = Identity;
+        out.defs = module
+            .defs()
+            .iter()
+            .map(|def| folder.fold_def(&module, &mut out, def))
+            .collect();
+
+        assert!(modules_structurally_eq(&module, &tokens, &out, &tokens));
+    }
+
+    // chunk0-4: type inference success/failure.
+    #[test]
+    fn type_checker_flags_binary_operand_mismatch() {
+        let (tokens, mut module) = parse_ok("def f(x: int, y: bool) = x + y\n");
+        let (_, errors) = check(&mut module, &tokens);
+        assert_eq!(errors.len(), 1);
+    }
+
+    #[test]
+    fn type_checker_accepts_matching_operand_types() {
+        let (tokens, mut module) = parse_ok("def f(x: int, y: int) = x + y\n");
+        let (_, errors) = check(&mut module, &tokens);
+        assert!(errors.is_empty());
+    }
+
+    #[test]
+    fn binary_operator_rejects_matching_unit_operands() {
+        let (tokens, mut module) = parse_ok("def f(x: (), y: ()) = x + y\n");
+        let (_, errors) = check(&mut module, &tokens);
+        assert_eq!(errors.len(), 1);
+        assert!(matches!(errors[0], TypeError::NonBaseOperand { .. }));
+    }
+
+    #[test]
+    fn comparison_operator_rejects_matching_pair_operands() {
+        let (tokens, mut module) = parse_ok("def f(x: (int, int), y: (int, int)) = x < y\n");
+        let (_, errors) = check(&mut module, &tokens);
+        assert_eq!(errors.len(), 1);
+        assert!(matches!(errors[0], TypeError::NonBaseOperand { .. }));
+    }
+
+    // chunk0-5: `if`/`then`/`else`.
+    #[test]
+    fn if_then_else_branches_must_typecheck_together() {
+        let (tokens, mut module) = parse_ok("def f(x: int, y: bool) = if x < 0 then x else y\n");
+        let (_, errors) = check(&mut module, &tokens);
+        assert_eq!(errors.len(), 1);
+    }
+
+    #[test]
+    fn if_then_else_with_matching_branches_typechecks() {
+        let (tokens, mut module) = parse_ok("def f(x: int, y: int) = if x < 0 then x else y\n");
+        let (_, errors) = check(&mut module, &tokens);
+        assert!(errors.is_empty());
+    }
+
+    // chunk0-6: lambda application.
+    #[test]
+    fn lambda_argument_type_mismatch_is_caught() {
+        let (tokens, mut module) = parse_ok("def f(y: bool) = (fn(x: int) = x) y\n");
+        let (_, errors) = check(&mut module, &tokens);
+        assert_eq!(errors.len(), 1);
+    }
+
+    #[test]
+    fn lambda_argument_type_match_is_accepted() {
+        let (tokens, mut module) = parse_ok("def f(y: int) = (fn(x: int) = x) y\n");
+        let (_, errors) = check(&mut module, &tokens);
+        assert!(errors.is_empty());
+    }
+
+    // chunk0-7: round-trip via the pretty-printer.
+    #[test]
+    fn roundtrip_handles_double_negation() {
+        assert_parse_roundtrip(&lex("def f(x: int) = - -x\n"));
+    }
+
+    #[test]
+    fn roundtrip_handles_a_representative_program() {
+        assert_parse_roundtrip(&lex(
+            "def f(x: int, y: int) : int =\n  \
+             let z = x ^ 2 - y * 3;\n  \
+             if z < 0 then -z else (fn(w) = w + 1) z\n",
+        ));
+    }
+}